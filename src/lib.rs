@@ -0,0 +1,16 @@
+//! Frappe is a small reactive-programming library built around two handle
+//! types: [`Stream`], a sequence of discrete events, and [`Signal`], a value
+//! that changes over time. [`Sink`] is the entry point used to push values
+//! into a stream from the outside world.
+//!
+//! Streams are combined through ordinary iterator-like combinators
+//! (`map`/`filter`/`fold`/...), while signals are sampled on demand with
+//! `sample`/`sample_with`. Most stream callbacks receive a `Cow<T>` so that a
+//! value shared between several observers isn't cloned unless an observer
+//! actually needs to own it.
+
+mod signal;
+mod stream;
+
+pub use crate::signal::Signal;
+pub use crate::stream::{Sink, Stream};
@@ -0,0 +1,147 @@
+//! Signals: values that change over time, sampled on demand.
+
+use std::sync::{Arc, Mutex};
+
+/// A lazily-sampled, type-erased value source. `visit` is the only method a
+/// `Signal` needs from its backing storage: it hands a reference to the
+/// current value to the given callback, recomputing it from upstream signals
+/// as needed.
+pub(crate) trait SignalCore<T>: Send + Sync {
+    fn visit(&self, f: &mut dyn FnMut(&T));
+}
+
+/// A value that changes over time. Cloning a `Signal` is cheap: it shares
+/// the same underlying storage as the original.
+pub struct Signal<T> {
+    pub(crate) inner: Arc<dyn SignalCore<T> + Send + Sync>,
+}
+
+impl<T> Clone for Signal<T> {
+    fn clone(&self) -> Self {
+        Signal {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<T: Default + Send + Sync + 'static> Default for Signal<T> {
+    fn default() -> Self {
+        Signal::constant(T::default())
+    }
+}
+
+struct ConstCore<T>(T);
+
+impl<T: Send + Sync> SignalCore<T> for ConstCore<T> {
+    fn visit(&self, f: &mut dyn FnMut(&T)) {
+        f(&self.0);
+    }
+}
+
+struct FromFnCore<F>(Mutex<F>);
+
+impl<T, F> SignalCore<T> for FromFnCore<F>
+where
+    F: FnMut() -> T + Send,
+{
+    fn visit(&self, f: &mut dyn FnMut(&T)) {
+        let value = (self.0.lock().unwrap())();
+        f(&value);
+    }
+}
+
+struct SigMapCore<T, U, F> {
+    parent: Arc<dyn SignalCore<T> + Send + Sync>,
+    f: Mutex<F>,
+    _marker: std::marker::PhantomData<fn() -> U>,
+}
+
+impl<T, U, F> SignalCore<U> for SigMapCore<T, U, F>
+where
+    T: Clone,
+    U: Send,
+    F: FnMut(T) -> U + Send,
+{
+    fn visit(&self, f: &mut dyn FnMut(&U)) {
+        self.parent.visit(&mut |t: &T| {
+            let u = (self.f.lock().unwrap())(t.clone());
+            f(&u);
+        });
+    }
+}
+
+struct SwitchCore<T> {
+    parent: Arc<dyn SignalCore<Signal<T>> + Send + Sync>,
+}
+
+impl<T> SignalCore<T> for SwitchCore<T> {
+    fn visit(&self, f: &mut dyn FnMut(&T)) {
+        self.parent.visit(&mut |inner: &Signal<T>| {
+            inner.inner.visit(f);
+        });
+    }
+}
+
+impl<T: Send + Sync + 'static> Signal<T> {
+    /// Creates a signal that always samples to the same value.
+    pub fn constant(value: T) -> Self {
+        Signal {
+            inner: Arc::new(ConstCore(value)),
+        }
+    }
+
+    /// Creates a signal whose value is recomputed by `f` on every sample.
+    pub fn from_fn<F>(f: F) -> Self
+    where
+        F: FnMut() -> T + Send + 'static,
+    {
+        Signal {
+            inner: Arc::new(FromFnCore(Mutex::new(f))),
+        }
+    }
+
+    /// Transforms this signal's value with `f`, recomputed on every sample.
+    pub fn map<U, F>(&self, f: F) -> Signal<U>
+    where
+        T: Clone,
+        U: Send + Sync + 'static,
+        F: FnMut(T) -> U + Send + 'static,
+    {
+        Signal {
+            inner: Arc::new(SigMapCore {
+                parent: self.inner.clone(),
+                f: Mutex::new(f),
+                _marker: std::marker::PhantomData,
+            }),
+        }
+    }
+
+    /// Gets a reference to the current value without cloning it.
+    pub fn sample_with<R>(&self, f: impl FnOnce(&T) -> R) -> R {
+        let mut f = Some(f);
+        let mut result = None;
+        self.inner.visit(&mut |v: &T| {
+            result = Some((f.take().unwrap())(v));
+        });
+        result.unwrap()
+    }
+}
+
+impl<T: Clone + Send + Sync + 'static> Signal<T> {
+    /// Gets a copy of the current value.
+    pub fn sample(&self) -> T {
+        self.sample_with(|v| v.clone())
+    }
+}
+
+impl<T: Send + Sync + 'static> Signal<Signal<T>> {
+    /// Collapses a signal-of-signals into a signal that always follows the
+    /// most recently produced inner signal.
+    pub fn switch(&self) -> Signal<T> {
+        Signal {
+            inner: Arc::new(SwitchCore {
+                parent: self.inner.clone(),
+            }),
+        }
+    }
+}
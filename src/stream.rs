@@ -0,0 +1,1273 @@
+//! Streams: sequences of discrete events, and the `Sink`s that feed them.
+
+use crate::signal::{Signal, SignalCore};
+use std::any::Any;
+use std::borrow::{Borrow, Cow};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex, Weak};
+
+/// A node that has subscribed to a stream's values. Combinators implement
+/// this to forward (possibly transformed) values to whatever they feed into.
+/// Returning `false` from `push` tells the source to drop this observer.
+pub(crate) trait RawObserver<T>: Send + Sync {
+    fn push(&self, value: &T) -> bool;
+    fn end(&self);
+}
+
+/// Queue + re-entrancy guard for a single node's outgoing values. Sending
+/// into a node that is already mid-dispatch (because, say, an observer of
+/// this very node sends back into it) just enqueues the value: the thread
+/// already driving the dispatch loop will pick it up, so no lock is ever
+/// held across a callback and no thread blocks on itself.
+struct SendState<T> {
+    pending: VecDeque<T>,
+    busy: bool,
+}
+
+type RawObserverFn<T> = Box<dyn FnMut(&T) -> bool + Send>;
+
+pub(crate) struct StreamNode<T> {
+    state: Mutex<SendState<T>>,
+    observers: Mutex<Vec<Weak<dyn RawObserver<T>>>>,
+    raw_observers: Mutex<Vec<RawObserverFn<T>>>,
+    ended: Mutex<bool>,
+    end_callbacks: Mutex<Vec<Box<dyn FnOnce() + Send>>>,
+    /// Last value sent, if this node remembers one (see `Stream::remember`).
+    /// Replayed to observers as they register, so late subscribers don't
+    /// miss it.
+    replay: Mutex<Option<T>>,
+}
+
+impl<T: Clone> StreamNode<T> {
+    pub(crate) fn new() -> Self {
+        StreamNode {
+            state: Mutex::new(SendState {
+                pending: VecDeque::new(),
+                busy: false,
+            }),
+            observers: Mutex::new(Vec::new()),
+            raw_observers: Mutex::new(Vec::new()),
+            ended: Mutex::new(false),
+            end_callbacks: Mutex::new(Vec::new()),
+            replay: Mutex::new(None),
+        }
+    }
+
+    pub(crate) fn send(&self, value: &T) {
+        if *self.ended.lock().unwrap() {
+            return;
+        }
+        let mut state = self.state.lock().unwrap();
+        state.pending.push_back(value.clone());
+        if state.busy {
+            return;
+        }
+        state.busy = true;
+        loop {
+            let next = state.pending.pop_front();
+            match next {
+                Some(v) => {
+                    drop(state);
+                    self.dispatch(&v);
+                    state = self.state.lock().unwrap();
+                }
+                None => {
+                    state.busy = false;
+                    break;
+                }
+            }
+        }
+    }
+
+    fn dispatch(&self, value: &T) {
+        // Never hold `raw_observers`/`observers` locked while calling into an
+        // observer: `push` is arbitrary user code that may call back into
+        // this very node (e.g. `Sink::end`, or registering another observer
+        // on the same stream from inside an `inspect`/`map` callback), and
+        // these `Mutex`es aren't reentrant. So each pass below pulls the
+        // current list out from under its lock, runs callbacks against that
+        // snapshot with no lock held, then reacquires the lock just long
+        // enough to drop whatever died and fold in anything registered in
+        // the meantime.
+        let raw_snapshot = std::mem::take(&mut *self.raw_observers.lock().unwrap());
+        let mut raw_survivors = Vec::with_capacity(raw_snapshot.len());
+        for mut f in raw_snapshot {
+            if f(value) {
+                raw_survivors.push(f);
+            }
+        }
+        {
+            let mut guard = self.raw_observers.lock().unwrap();
+            raw_survivors.append(&mut guard);
+            *guard = raw_survivors;
+        }
+
+        let snapshot = self.observers.lock().unwrap().clone();
+        let mut dead = Vec::new();
+        for weak in &snapshot {
+            let alive = match weak.upgrade() {
+                Some(o) => o.push(value),
+                None => false,
+            };
+            if !alive {
+                dead.push(weak.clone());
+            }
+        }
+        if !dead.is_empty() {
+            self.observers
+                .lock()
+                .unwrap()
+                .retain(|w| !dead.iter().any(|d| Weak::ptr_eq(d, w)));
+        }
+    }
+
+    pub(crate) fn end(&self) {
+        {
+            let mut ended = self.ended.lock().unwrap();
+            if *ended {
+                return;
+            }
+            *ended = true;
+        }
+        let observers = self.observers.lock().unwrap().clone();
+        for weak in observers {
+            if let Some(o) = weak.upgrade() {
+                o.end();
+            }
+        }
+        let callbacks = std::mem::take(&mut *self.end_callbacks.lock().unwrap());
+        for cb in callbacks {
+            cb();
+        }
+    }
+
+    pub(crate) fn on_end(&self, f: impl FnOnce() + Send + 'static) {
+        let ended = self.ended.lock().unwrap();
+        if *ended {
+            drop(ended);
+            f();
+        } else {
+            self.end_callbacks.lock().unwrap().push(Box::new(f));
+        }
+    }
+
+    pub(crate) fn register(&self, obs: Weak<dyn RawObserver<T>>) {
+        let replay = self.replay.lock().unwrap().clone();
+        if let Some(v) = replay {
+            if let Some(o) = obs.upgrade() {
+                o.push(&v);
+            }
+        }
+        self.observers.lock().unwrap().push(obs);
+    }
+
+    pub(crate) fn register_raw(&self, mut f: RawObserverFn<T>) {
+        let replay = self.replay.lock().unwrap().clone();
+        if let Some(v) = replay {
+            if !f(&v) {
+                return;
+            }
+        }
+        self.raw_observers.lock().unwrap().push(f);
+    }
+
+    pub(crate) fn set_replay(&self, value: T) {
+        *self.replay.lock().unwrap() = Some(value);
+    }
+}
+
+/// Registers `observer` as a (weak) subscriber of `source`.
+fn register<T: Clone, O>(source: &Stream<T>, observer: &Arc<O>)
+where
+    O: RawObserver<T> + 'static,
+{
+    let erased: Arc<dyn RawObserver<T>> = observer.clone();
+    source.node.register(Arc::downgrade(&erased));
+}
+
+/// A sequence of discrete events. Cloning a `Stream` is cheap: it shares the
+/// same underlying node (and keeps the same upstream chain alive) as the
+/// original.
+pub struct Stream<T> {
+    pub(crate) node: Arc<StreamNode<T>>,
+    /// Keeps this stream's upstream observer chain alive for as long as this
+    /// handle (or any clone/descendant of it) exists.
+    pub(crate) keep_alive: Arc<dyn Any + Send + Sync>,
+}
+
+impl<T> Clone for Stream<T> {
+    fn clone(&self) -> Self {
+        Stream {
+            node: self.node.clone(),
+            keep_alive: self.keep_alive.clone(),
+        }
+    }
+}
+
+/// The entry point used to push values into a stream from the outside
+/// world.
+pub struct Sink<T> {
+    node: Arc<StreamNode<T>>,
+}
+
+impl<T> Clone for Sink<T> {
+    fn clone(&self) -> Self {
+        Sink {
+            node: self.node.clone(),
+        }
+    }
+}
+
+impl<T: Clone + Send + 'static> Sink<T> {
+    /// Creates a new, empty sink.
+    pub fn new() -> Self {
+        Sink {
+            node: Arc::new(StreamNode::new()),
+        }
+    }
+
+    /// Gets a handle to this sink's stream of sent values.
+    pub fn stream(&self) -> Stream<T> {
+        Stream {
+            node: self.node.clone(),
+            keep_alive: Arc::new(()),
+        }
+    }
+
+    /// Sends a single value into the stream.
+    pub fn send(&self, value: T) {
+        self.node.send(&value);
+    }
+
+    /// Sends every value produced by `iter`, in order.
+    pub fn feed<I>(&self, iter: I)
+    where
+        I: IntoIterator,
+        I::Item: Borrow<T>,
+    {
+        for item in iter {
+            self.send(item.borrow().clone());
+        }
+    }
+
+    /// Marks the stream as closed: no further values will be delivered, and
+    /// `on_end` hooks registered on it (and on anything derived from it)
+    /// fire exactly once.
+    pub fn end(&self) {
+        self.node.end();
+    }
+}
+
+impl<T: Clone + Send + 'static> Default for Sink<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// ---- basic combinators -----------------------------------------------
+
+struct MapNode<T, U, F> {
+    target: Arc<StreamNode<U>>,
+    f: Mutex<F>,
+    _source: Stream<T>,
+}
+
+impl<T, U, F> RawObserver<T> for MapNode<T, U, F>
+where
+    T: Clone + Send + 'static,
+    U: Clone + Send + 'static,
+    F: FnMut(Cow<'_, T>) -> U + Send,
+{
+    fn push(&self, value: &T) -> bool {
+        let out = (self.f.lock().unwrap())(Cow::Borrowed(value));
+        self.target.send(&out);
+        true
+    }
+
+    fn end(&self) {
+        self.target.end();
+    }
+}
+
+struct FilterNode<T, F> {
+    target: Arc<StreamNode<T>>,
+    f: Mutex<F>,
+    _source: Stream<T>,
+}
+
+impl<T, F> RawObserver<T> for FilterNode<T, F>
+where
+    T: Clone + Send + 'static,
+    F: FnMut(&T) -> bool + Send,
+{
+    fn push(&self, value: &T) -> bool {
+        if (self.f.lock().unwrap())(value) {
+            self.target.send(value);
+        }
+        true
+    }
+
+    fn end(&self) {
+        self.target.end();
+    }
+}
+
+struct FilterMapNode<T, U, F> {
+    target: Arc<StreamNode<U>>,
+    f: Mutex<F>,
+    _source: Stream<T>,
+}
+
+impl<T, U, F> RawObserver<T> for FilterMapNode<T, U, F>
+where
+    T: Clone + Send + 'static,
+    U: Clone + Send + 'static,
+    F: FnMut(Cow<'_, T>) -> Option<U> + Send,
+{
+    fn push(&self, value: &T) -> bool {
+        if let Some(out) = (self.f.lock().unwrap())(Cow::Borrowed(value)) {
+            self.target.send(&out);
+        }
+        true
+    }
+
+    fn end(&self) {
+        self.target.end();
+    }
+}
+
+struct InspectNode<T, F> {
+    target: Arc<StreamNode<T>>,
+    f: Mutex<F>,
+    _source: Stream<T>,
+}
+
+impl<T, F> RawObserver<T> for InspectNode<T, F>
+where
+    T: Clone + Send + 'static,
+    F: FnMut(Cow<'_, T>) + Send,
+{
+    fn push(&self, value: &T) -> bool {
+        (self.f.lock().unwrap())(Cow::Borrowed(value));
+        self.target.send(value);
+        true
+    }
+
+    fn end(&self) {
+        self.target.end();
+    }
+}
+
+struct FoldCore<T, Acc, F> {
+    value: Mutex<Option<Acc>>,
+    f: Mutex<F>,
+    _source: Stream<T>,
+}
+
+impl<T, Acc, F> RawObserver<T> for FoldCore<T, Acc, F>
+where
+    T: Clone + Send + 'static,
+    Acc: Send + 'static,
+    F: FnMut(Acc, Cow<'_, T>) -> Acc + Send,
+{
+    fn push(&self, value: &T) -> bool {
+        let mut guard = self.value.lock().unwrap();
+        let old = guard.take().unwrap();
+        let new = (self.f.lock().unwrap())(old, Cow::Borrowed(value));
+        *guard = Some(new);
+        true
+    }
+
+    fn end(&self) {}
+}
+
+// `Acc` only needs `Send` here, not `Sync`: the value is exclusively reached
+// through a `Mutex`, which is `Sync` for any `Send` payload regardless of
+// the payload's own `Sync`-ness. This is what lets `Stream<Result<A, B>>::
+// try_fold` fold into a `Result<Acc, B>` accumulator without also demanding
+// `B: Sync` - its surrounding impl block only bounds `B: Send`. Sampling the
+// resulting `Signal` still requires `Send + Sync` on the whole value, same
+// as any other `Signal` (see `signal.rs`), so this doesn't make non-Sync
+// accumulators sample-able - it just keeps `fold`'s own bound from being
+// stricter than what `try_fold` actually needs to compile.
+impl<T, Acc, F> SignalCore<Acc> for FoldCore<T, Acc, F>
+where
+    T: Send,
+    Acc: Send,
+    F: Send,
+{
+    fn visit(&self, f: &mut dyn FnMut(&Acc)) {
+        f(self.value.lock().unwrap().as_ref().unwrap());
+    }
+}
+
+struct HoldCore<T> {
+    value: Mutex<T>,
+    _source: Stream<T>,
+}
+
+impl<T: Clone + Send + 'static> RawObserver<T> for HoldCore<T> {
+    fn push(&self, value: &T) -> bool {
+        *self.value.lock().unwrap() = value.clone();
+        true
+    }
+
+    fn end(&self) {}
+}
+
+impl<T: Send + Sync + 'static> SignalCore<T> for HoldCore<T> {
+    fn visit(&self, f: &mut dyn FnMut(&T)) {
+        f(&self.value.lock().unwrap());
+    }
+}
+
+struct HoldIfCore<T, F> {
+    value: Mutex<T>,
+    pred: Mutex<F>,
+    _source: Stream<T>,
+}
+
+impl<T, F> RawObserver<T> for HoldIfCore<T, F>
+where
+    T: Clone + Send + 'static,
+    F: FnMut(&T) -> bool + Send,
+{
+    fn push(&self, value: &T) -> bool {
+        if (self.pred.lock().unwrap())(value) {
+            *self.value.lock().unwrap() = value.clone();
+        }
+        true
+    }
+
+    fn end(&self) {}
+}
+
+impl<T, F> SignalCore<T> for HoldIfCore<T, F>
+where
+    T: Send + Sync + 'static,
+    F: Send,
+{
+    fn visit(&self, f: &mut dyn FnMut(&T)) {
+        f(&self.value.lock().unwrap());
+    }
+}
+
+struct SnapshotNode<S, E, U, F> {
+    target: Arc<StreamNode<U>>,
+    signal: Signal<S>,
+    f: Mutex<F>,
+    _source: Stream<E>,
+}
+
+impl<S, E, U, F> RawObserver<E> for SnapshotNode<S, E, U, F>
+where
+    S: Clone + Send + Sync + 'static,
+    E: Clone + Send + 'static,
+    U: Clone + Send + 'static,
+    F: FnMut(S, &E) -> U + Send,
+{
+    fn push(&self, value: &E) -> bool {
+        let s = self.signal.sample();
+        let out = (self.f.lock().unwrap())(s, value);
+        self.target.send(&out);
+        true
+    }
+
+    fn end(&self) {
+        self.target.end();
+    }
+}
+
+struct MapNNode<T, U, F> {
+    target: Arc<StreamNode<U>>,
+    f: Mutex<F>,
+    _source: Stream<T>,
+}
+
+/// Handle passed to a `map_n` callback, letting it emit zero or more output
+/// values for a single input event.
+pub struct StreamSender<'a, U> {
+    target: &'a StreamNode<U>,
+}
+
+impl<'a, U: Clone + Send + 'static> StreamSender<'a, U> {
+    pub fn send(&self, value: U) {
+        self.target.send(&value);
+    }
+}
+
+impl<T, U, F> RawObserver<T> for MapNNode<T, U, F>
+where
+    T: Clone + Send + 'static,
+    U: Clone + Send + 'static,
+    F: FnMut(Cow<'_, T>, &StreamSender<'_, U>) + Send,
+{
+    fn push(&self, value: &T) -> bool {
+        let sender = StreamSender {
+            target: &self.target,
+        };
+        (self.f.lock().unwrap())(Cow::Borrowed(value), &sender);
+        true
+    }
+
+    fn end(&self) {
+        self.target.end();
+    }
+}
+
+// ---- merge / merge_with -------------------------------------------------
+
+struct MergeWithNode<L, R, U, F1, F2> {
+    target: Arc<StreamNode<U>>,
+    f1: Mutex<F1>,
+    f2: Mutex<F2>,
+    ended: Mutex<(bool, bool)>,
+    _left: Stream<L>,
+    _right: Stream<R>,
+    _marker: std::marker::PhantomData<fn(R, F2)>,
+}
+
+impl<L, R, U, F1, F2> MergeWithNode<L, R, U, F1, F2>
+where
+    U: Clone + Send + 'static,
+{
+    fn push_left(&self, value: &L) -> bool
+    where
+        L: Clone,
+        F1: FnMut(Cow<'_, L>) -> U,
+    {
+        let out = (self.f1.lock().unwrap())(Cow::Borrowed(value));
+        self.target.send(&out);
+        true
+    }
+
+    fn push_right(&self, value: &R) -> bool
+    where
+        R: Clone,
+        F2: FnMut(Cow<'_, R>) -> U,
+    {
+        let out = (self.f2.lock().unwrap())(Cow::Borrowed(value));
+        self.target.send(&out);
+        true
+    }
+
+    fn end_left(&self) {
+        let mut ended = self.ended.lock().unwrap();
+        ended.0 = true;
+        if ended.1 {
+            drop(ended);
+            self.target.end();
+        }
+    }
+
+    fn end_right(&self) {
+        let mut ended = self.ended.lock().unwrap();
+        ended.1 = true;
+        if ended.0 {
+            drop(ended);
+            self.target.end();
+        }
+    }
+}
+
+struct MergeLeft<L, R, U, F1, F2>(Arc<MergeWithNode<L, R, U, F1, F2>>);
+struct MergeRight<L, R, U, F1, F2>(Arc<MergeWithNode<L, R, U, F1, F2>>);
+
+impl<L, R, U, F1, F2> RawObserver<L> for MergeLeft<L, R, U, F1, F2>
+where
+    L: Clone + Send + 'static,
+    R: Send + 'static,
+    U: Clone + Send + 'static,
+    F1: FnMut(Cow<'_, L>) -> U + Send,
+    F2: Send,
+{
+    fn push(&self, value: &L) -> bool {
+        self.0.push_left(value)
+    }
+
+    fn end(&self) {
+        self.0.end_left();
+    }
+}
+
+impl<L, R, U, F1, F2> RawObserver<R> for MergeRight<L, R, U, F1, F2>
+where
+    L: Send + 'static,
+    R: Clone + Send + 'static,
+    U: Clone + Send + 'static,
+    F1: Send,
+    F2: FnMut(Cow<'_, R>) -> U + Send,
+{
+    fn push(&self, value: &R) -> bool {
+        self.0.push_right(value)
+    }
+
+    fn end(&self) {
+        self.0.end_right();
+    }
+}
+
+/// Forwards values from one inner stream into a `switch`/`flatten_concurrent`
+/// target. Kept alive for as long as its inner stream should stay connected.
+struct InnerForward<T> {
+    target: Arc<StreamNode<T>>,
+}
+
+impl<T: Clone + Send + 'static> RawObserver<T> for InnerForward<T> {
+    fn push(&self, value: &T) -> bool {
+        self.target.send(value);
+        true
+    }
+
+    fn end(&self) {}
+}
+
+struct SwitchNode<T> {
+    target: Arc<StreamNode<T>>,
+    /// The forwarder for the most recently emitted inner stream. Replacing
+    /// it drops the previous one, which deregisters it (via its now-dead
+    /// `Weak`) the next time that old inner stream sends a value.
+    current: Mutex<Option<Arc<InnerForward<T>>>>,
+    _source: Stream<Stream<T>>,
+}
+
+impl<T: Clone + Send + 'static> RawObserver<Stream<T>> for SwitchNode<T> {
+    fn push(&self, inner: &Stream<T>) -> bool {
+        let forward = Arc::new(InnerForward {
+            target: self.target.clone(),
+        });
+        register(inner, &forward);
+        *self.current.lock().unwrap() = Some(forward);
+        true
+    }
+
+    fn end(&self) {
+        self.target.end();
+    }
+}
+
+struct FlattenConcurrentNode<T> {
+    target: Arc<StreamNode<T>>,
+    forwards: Mutex<Vec<Arc<InnerForward<T>>>>,
+    _source: Stream<Stream<T>>,
+}
+
+impl<T: Clone + Send + 'static> RawObserver<Stream<T>> for FlattenConcurrentNode<T> {
+    fn push(&self, inner: &Stream<T>) -> bool {
+        let forward = Arc::new(InnerForward {
+            target: self.target.clone(),
+        });
+        register(inner, &forward);
+        self.forwards.lock().unwrap().push(forward);
+        true
+    }
+
+    fn end(&self) {
+        self.target.end();
+    }
+}
+
+struct TakeNode<T> {
+    target: Arc<StreamNode<T>>,
+    remaining: Mutex<usize>,
+    _source: Stream<T>,
+}
+
+impl<T: Clone + Send + 'static> RawObserver<T> for TakeNode<T> {
+    fn push(&self, value: &T) -> bool {
+        let mut remaining = self.remaining.lock().unwrap();
+        if *remaining == 0 {
+            return false;
+        }
+        *remaining -= 1;
+        let last = *remaining == 0;
+        drop(remaining);
+        self.target.send(value);
+        if last {
+            self.target.end();
+            false
+        } else {
+            true
+        }
+    }
+
+    fn end(&self) {
+        self.target.end();
+    }
+}
+
+struct SkipNode<T> {
+    target: Arc<StreamNode<T>>,
+    remaining: Mutex<usize>,
+    _source: Stream<T>,
+}
+
+impl<T: Clone + Send + 'static> RawObserver<T> for SkipNode<T> {
+    fn push(&self, value: &T) -> bool {
+        let mut remaining = self.remaining.lock().unwrap();
+        if *remaining > 0 {
+            *remaining -= 1;
+            return true;
+        }
+        drop(remaining);
+        self.target.send(value);
+        true
+    }
+
+    fn end(&self) {
+        self.target.end();
+    }
+}
+
+struct TakeWhileNode<T, F> {
+    target: Arc<StreamNode<T>>,
+    pred: Mutex<F>,
+    _source: Stream<T>,
+}
+
+impl<T, F> RawObserver<T> for TakeWhileNode<T, F>
+where
+    T: Clone + Send + 'static,
+    F: FnMut(&T) -> bool + Send,
+{
+    fn push(&self, value: &T) -> bool {
+        if !(self.pred.lock().unwrap())(value) {
+            self.target.end();
+            return false;
+        }
+        self.target.send(value);
+        true
+    }
+
+    fn end(&self) {
+        self.target.end();
+    }
+}
+
+struct SkipWhileNode<T, F> {
+    target: Arc<StreamNode<T>>,
+    pred: Mutex<F>,
+    skipping: Mutex<bool>,
+    _source: Stream<T>,
+}
+
+impl<T, F> RawObserver<T> for SkipWhileNode<T, F>
+where
+    T: Clone + Send + 'static,
+    F: FnMut(&T) -> bool + Send,
+{
+    fn push(&self, value: &T) -> bool {
+        let mut skipping = self.skipping.lock().unwrap();
+        if *skipping {
+            if (self.pred.lock().unwrap())(value) {
+                return true;
+            }
+            *skipping = false;
+        }
+        drop(skipping);
+        self.target.send(value);
+        true
+    }
+
+    fn end(&self) {
+        self.target.end();
+    }
+}
+
+struct RememberNode<T> {
+    target: Arc<StreamNode<T>>,
+    _source: Stream<T>,
+}
+
+impl<T: Clone + Send + 'static> RawObserver<T> for RememberNode<T> {
+    fn push(&self, value: &T) -> bool {
+        self.target.set_replay(value.clone());
+        self.target.send(value);
+        true
+    }
+
+    fn end(&self) {
+        self.target.end();
+    }
+}
+
+// ---- public API -----------------------------------------------------
+
+impl<T: Clone + Send + 'static> Stream<T> {
+    /// Transforms every value with `f`.
+    pub fn map<U, F>(&self, f: F) -> Stream<U>
+    where
+        U: Clone + Send + 'static,
+        F: FnMut(Cow<'_, T>) -> U + Send + 'static,
+    {
+        let target = Arc::new(StreamNode::new());
+        let node = Arc::new(MapNode {
+            target: target.clone(),
+            f: Mutex::new(f),
+            _source: self.clone(),
+        });
+        register(self, &node);
+        Stream {
+            node: target,
+            keep_alive: node,
+        }
+    }
+
+    /// Keeps only the values for which `pred` returns `true`.
+    pub fn filter<F>(&self, pred: F) -> Stream<T>
+    where
+        F: FnMut(&T) -> bool + Send + 'static,
+    {
+        let target = Arc::new(StreamNode::new());
+        let node = Arc::new(FilterNode {
+            target: target.clone(),
+            f: Mutex::new(pred),
+            _source: self.clone(),
+        });
+        register(self, &node);
+        Stream {
+            node: target,
+            keep_alive: node,
+        }
+    }
+
+    /// Transforms every value with `f`, dropping events for which it
+    /// returns `None`.
+    pub fn filter_map<U, F>(&self, f: F) -> Stream<U>
+    where
+        U: Clone + Send + 'static,
+        F: FnMut(Cow<'_, T>) -> Option<U> + Send + 'static,
+    {
+        let target = Arc::new(StreamNode::new());
+        let node = Arc::new(FilterMapNode {
+            target: target.clone(),
+            f: Mutex::new(f),
+            _source: self.clone(),
+        });
+        register(self, &node);
+        Stream {
+            node: target,
+            keep_alive: node,
+        }
+    }
+
+    /// Runs `f` for its side effect on every value, passing the value
+    /// through unchanged.
+    pub fn inspect<F>(&self, f: F) -> Stream<T>
+    where
+        F: FnMut(Cow<'_, T>) + Send + 'static,
+    {
+        let target = Arc::new(StreamNode::new());
+        let node = Arc::new(InspectNode {
+            target: target.clone(),
+            f: Mutex::new(f),
+            _source: self.clone(),
+        });
+        register(self, &node);
+        Stream {
+            node: target,
+            keep_alive: node,
+        }
+    }
+
+    /// Accumulates every value into a running total, exposed as a `Signal`.
+    pub fn fold<Acc, F>(&self, init: Acc, f: F) -> Signal<Acc>
+    where
+        Acc: Send + 'static,
+        F: FnMut(Acc, Cow<'_, T>) -> Acc + Send + 'static,
+    {
+        let node = Arc::new(FoldCore {
+            value: Mutex::new(Some(init)),
+            f: Mutex::new(f),
+            _source: self.clone(),
+        });
+        register(self, &node);
+        Signal { inner: node }
+    }
+
+    /// Same as [`fold`](Stream::fold): the callback gets each event as a
+    /// borrowed `Cow`, and clones it (e.g. with `into_owned`) only if it
+    /// needs to own it.
+    pub fn fold_clone<Acc, F>(&self, init: Acc, f: F) -> Signal<Acc>
+    where
+        Acc: Send + 'static,
+        F: FnMut(Acc, Cow<'_, T>) -> Acc + Send + 'static,
+    {
+        self.fold(init, f)
+    }
+
+    /// Stores the most recent value as a `Signal`, starting at `init`.
+    pub fn hold(&self, init: T) -> Signal<T>
+    where
+        T: Send + Sync,
+    {
+        let node = Arc::new(HoldCore {
+            value: Mutex::new(init),
+            _source: self.clone(),
+        });
+        register(self, &node);
+        Signal { inner: node }
+    }
+
+    /// Like [`hold`](Stream::hold), but only stores values for which `pred`
+    /// returns `true`.
+    pub fn hold_if<F>(&self, init: T, pred: F) -> Signal<T>
+    where
+        T: Send + Sync,
+        F: FnMut(&T) -> bool + Send + 'static,
+    {
+        let node = Arc::new(HoldIfCore {
+            value: Mutex::new(init),
+            pred: Mutex::new(pred),
+            _source: self.clone(),
+        });
+        register(self, &node);
+        Signal { inner: node }
+    }
+
+    /// Registers a raw, low-level observer: `f` is called with every value
+    /// sent while it keeps returning `true`; returning `false` removes it.
+    /// Unlike the other combinators, this doesn't return a handle - the
+    /// observer lives as long as the stream itself.
+    pub fn observe<F>(&self, f: F)
+    where
+        F: FnMut(&T) -> bool + Send + 'static,
+    {
+        self.node.register_raw(Box::new(f));
+    }
+
+    /// Accumulates every value into a collection, exposed as a `Signal`.
+    pub fn collect<C>(&self) -> Signal<C>
+    where
+        C: Default + Extend<T> + Send + Sync + 'static,
+    {
+        self.fold(C::default(), |mut acc, v| {
+            acc.extend(std::iter::once(v.into_owned()));
+            acc
+        })
+    }
+
+    /// Calls `f` for each event with a sender that can emit zero or more
+    /// values downstream.
+    pub fn map_n<U, F>(&self, f: F) -> Stream<U>
+    where
+        U: Clone + Send + 'static,
+        F: FnMut(Cow<'_, T>, &StreamSender<'_, U>) + Send + 'static,
+    {
+        let target = Arc::new(StreamNode::new());
+        let node = Arc::new(MapNNode {
+            target: target.clone(),
+            f: Mutex::new(f),
+            _source: self.clone(),
+        });
+        register(self, &node);
+        Stream {
+            node: target,
+            keep_alive: node,
+        }
+    }
+
+    /// Merges this stream with `other` of the same type.
+    pub fn merge(&self, other: &Stream<T>) -> Stream<T> {
+        self.merge_with(other, |v| v.into_owned(), |v| v.into_owned())
+    }
+
+    /// Merges this stream with `other`, converting each side's values with
+    /// `f1`/`f2` into a common output type.
+    pub fn merge_with<R, U, F1, F2>(&self, other: &Stream<R>, f1: F1, f2: F2) -> Stream<U>
+    where
+        R: Clone + Send + 'static,
+        U: Clone + Send + 'static,
+        F1: FnMut(Cow<'_, T>) -> U + Send + 'static,
+        F2: FnMut(Cow<'_, R>) -> U + Send + 'static,
+    {
+        let target = Arc::new(StreamNode::new());
+        let node = Arc::new(MergeWithNode {
+            target: target.clone(),
+            f1: Mutex::new(f1),
+            f2: Mutex::new(f2),
+            ended: Mutex::new((false, false)),
+            _left: self.clone(),
+            _right: other.clone(),
+            _marker: std::marker::PhantomData,
+        });
+
+        let left = Arc::new(MergeLeft(node.clone()));
+        register(self, &left);
+        let right = Arc::new(MergeRight(node));
+        register(other, &right);
+
+        Stream {
+            node: target,
+            keep_alive: Arc::new((left, right)),
+        }
+    }
+
+    /// Registers a hook that fires exactly once, when this stream ends (see
+    /// [`Sink::end`]). If the stream has already ended, `f` runs
+    /// immediately.
+    pub fn on_end<F>(&self, f: F)
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        self.node.on_end(f);
+    }
+
+    /// Maps every value to a new stream, then forwards values from whichever
+    /// one was produced most recently (see [`Stream<Stream<T>>::switch`]).
+    pub fn flat_map<U, F>(&self, f: F) -> Stream<U>
+    where
+        U: Clone + Send + 'static,
+        F: FnMut(Cow<'_, T>) -> Stream<U> + Send + 'static,
+    {
+        self.map(f).switch()
+    }
+
+    /// Forwards only the first `n` values, then ends and disconnects from
+    /// the source.
+    pub fn take(&self, n: usize) -> Stream<T> {
+        let target = Arc::new(StreamNode::new());
+        if n == 0 {
+            // No source registration needed: zero values are ever taken, so
+            // the returned stream is already done.
+            target.end();
+            return Stream {
+                node: target,
+                keep_alive: Arc::new(()),
+            };
+        }
+        let node = Arc::new(TakeNode {
+            target: target.clone(),
+            remaining: Mutex::new(n),
+            _source: self.clone(),
+        });
+        register(self, &node);
+        Stream {
+            node: target,
+            keep_alive: node,
+        }
+    }
+
+    /// Drops the first `n` values, forwarding everything after them.
+    pub fn skip(&self, n: usize) -> Stream<T> {
+        let target = Arc::new(StreamNode::new());
+        let node = Arc::new(SkipNode {
+            target: target.clone(),
+            remaining: Mutex::new(n),
+            _source: self.clone(),
+        });
+        register(self, &node);
+        Stream {
+            node: target,
+            keep_alive: node,
+        }
+    }
+
+    /// Forwards values while `pred` holds, then ends and disconnects from
+    /// the source as soon as it returns `false`.
+    pub fn take_while<F>(&self, pred: F) -> Stream<T>
+    where
+        F: FnMut(&T) -> bool + Send + 'static,
+    {
+        let target = Arc::new(StreamNode::new());
+        let node = Arc::new(TakeWhileNode {
+            target: target.clone(),
+            pred: Mutex::new(pred),
+            _source: self.clone(),
+        });
+        register(self, &node);
+        Stream {
+            node: target,
+            keep_alive: node,
+        }
+    }
+
+    /// Drops values while `pred` holds, then forwards everything from the
+    /// first value for which it returns `false` onward.
+    pub fn skip_while<F>(&self, pred: F) -> Stream<T>
+    where
+        F: FnMut(&T) -> bool + Send + 'static,
+    {
+        let target = Arc::new(StreamNode::new());
+        let node = Arc::new(SkipWhileNode {
+            target: target.clone(),
+            pred: Mutex::new(pred),
+            skipping: Mutex::new(true),
+            _source: self.clone(),
+        });
+        register(self, &node);
+        Stream {
+            node: target,
+            keep_alive: node,
+        }
+    }
+
+    /// Remembers the last value sent, replaying it immediately to any
+    /// observer that registers afterward (so a late subscriber doesn't miss
+    /// whatever already happened).
+    pub fn remember(&self) -> Stream<T> {
+        let target = Arc::new(StreamNode::new());
+        let node = Arc::new(RememberNode {
+            target: target.clone(),
+            _source: self.clone(),
+        });
+        register(self, &node);
+        Stream {
+            node: target,
+            keep_alive: node,
+        }
+    }
+}
+
+impl<T: Clone + Send + 'static> Stream<Stream<T>> {
+    /// Collapses a stream-of-streams into a stream that always follows the
+    /// most recently emitted inner stream, disconnecting from whichever one
+    /// was active before.
+    pub fn switch(&self) -> Stream<T> {
+        let target = Arc::new(StreamNode::new());
+        let node = Arc::new(SwitchNode {
+            target: target.clone(),
+            current: Mutex::new(None),
+            _source: self.clone(),
+        });
+        register(self, &node);
+        Stream {
+            node: target,
+            keep_alive: node,
+        }
+    }
+
+    /// Like [`switch`](Self::switch), but keeps every inner stream
+    /// forwarding concurrently instead of disconnecting from the previous
+    /// one - equivalent to merging all of them together as they arrive.
+    pub fn flatten_concurrent(&self) -> Stream<T> {
+        let target = Arc::new(StreamNode::new());
+        let node = Arc::new(FlattenConcurrentNode {
+            target: target.clone(),
+            forwards: Mutex::new(Vec::new()),
+            _source: self.clone(),
+        });
+        register(self, &node);
+        Stream {
+            node: target,
+            keep_alive: node,
+        }
+    }
+}
+
+impl<T: Clone + Send + Sync + 'static> Signal<T> {
+    /// Samples this signal's current value whenever `stream` fires,
+    /// combining them with `f` into a new stream.
+    pub fn snapshot<E, U, F>(&self, stream: &Stream<E>, f: F) -> Stream<U>
+    where
+        E: Clone + Send + 'static,
+        U: Clone + Send + 'static,
+        F: FnMut(T, &E) -> U + Send + 'static,
+    {
+        let target = Arc::new(StreamNode::new());
+        let node = Arc::new(SnapshotNode {
+            target: target.clone(),
+            signal: self.clone(),
+            f: Mutex::new(f),
+            _source: stream.clone(),
+        });
+        register(stream, &node);
+        Stream {
+            node: target,
+            keep_alive: node,
+        }
+    }
+}
+
+impl<A: Clone + Send + 'static, B: Clone + Send + 'static> Stream<Result<A, B>> {
+    /// Splits a stream of `Result`s into its `Ok` and `Err` streams.
+    pub fn split(&self) -> (Stream<A>, Stream<B>) {
+        (self.filter_first(), self.filter_second())
+    }
+
+    /// Keeps only the `Ok` values.
+    pub fn filter_first(&self) -> Stream<A> {
+        self.filter_map(|r| r.into_owned().ok())
+    }
+
+    /// Keeps only the `Err` values.
+    pub fn filter_second(&self) -> Stream<B> {
+        self.filter_map(|r| r.into_owned().err())
+    }
+
+    /// Transforms `Ok` values with `f`, passing `Err` values through
+    /// unchanged.
+    pub fn map_ok<U, F>(&self, mut f: F) -> Stream<Result<U, B>>
+    where
+        U: Clone + Send + 'static,
+        F: FnMut(&A) -> U + Send + 'static,
+    {
+        self.map(move |r: Cow<'_, Result<A, B>>| match &*r {
+            Ok(a) => Ok(f(a)),
+            Err(e) => Err(e.clone()),
+        })
+    }
+
+    /// Transforms `Err` values with `f`, passing `Ok` values through
+    /// unchanged.
+    pub fn map_err<E2, F>(&self, mut f: F) -> Stream<Result<A, E2>>
+    where
+        E2: Clone + Send + 'static,
+        F: FnMut(&B) -> E2 + Send + 'static,
+    {
+        self.map(move |r: Cow<'_, Result<A, B>>| match &*r {
+            Ok(a) => Ok(a.clone()),
+            Err(e) => Err(f(e)),
+        })
+    }
+
+    /// Chains `Ok` values into `f`, passing `Err` values through unchanged.
+    /// `f`'s error type only needs to convert into `B` via [`From`], so it
+    /// doesn't have to match exactly.
+    pub fn and_then<U, E2, F>(&self, mut f: F) -> Stream<Result<U, B>>
+    where
+        U: Clone + Send + 'static,
+        E2: Send + 'static,
+        B: From<E2>,
+        F: FnMut(&A) -> Result<U, E2> + Send + 'static,
+    {
+        self.map(move |r: Cow<'_, Result<A, B>>| match &*r {
+            Ok(a) => f(a).map_err(B::from),
+            Err(e) => Err(e.clone()),
+        })
+    }
+
+    /// Chains `Err` values into `f`, passing `Ok` values through unchanged.
+    pub fn or_else<F>(&self, mut f: F) -> Stream<Result<A, B>>
+    where
+        F: FnMut(&B) -> Result<A, B> + Send + 'static,
+    {
+        self.map(move |r: Cow<'_, Result<A, B>>| match &*r {
+            Ok(a) => Ok(a.clone()),
+            Err(e) => f(e),
+        })
+    }
+
+    /// Folds only the `Ok` values into `Acc`; the first `Err` latches the
+    /// result and later values (of either kind) no longer affect it.
+    pub fn try_fold<Acc, F>(&self, init: Acc, mut f: F) -> Signal<Result<Acc, B>>
+    where
+        Acc: Clone + Send + Sync + 'static,
+        F: FnMut(Acc, &A) -> Acc + Send + 'static,
+    {
+        self.fold(Ok(init), move |acc, r| match acc {
+            Err(e) => Err(e),
+            Ok(acc) => match &*r {
+                Ok(a) => Ok(f(acc, a)),
+                Err(e) => Err(e.clone()),
+            },
+        })
+    }
+}
+
+impl<T: Clone + Send + 'static> Stream<Option<T>> {
+    /// Keeps only the `Some` values.
+    pub fn filter_some(&self) -> Stream<T> {
+        self.filter_map(|o| o.into_owned())
+    }
+}
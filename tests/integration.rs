@@ -26,7 +26,7 @@ fn stream_operations() {
     });
     let s_last_pos = stream.hold_if(0, |a| *a > 0);
 
-    sink.feed(&[5, 8, 13, -2, 42, -33]);
+    sink.feed([5, 8, 13, -2, 42, -33]);
 
     assert_eq!(s_string.sample(), ["5", "8", "13", "-2", "42", "-33"]);
     assert_eq!(s_odd.sample(), [5, 13, -33]);
@@ -234,7 +234,7 @@ fn stream_collect() {
     let s_set: Signal<BTreeSet<_>> = stream.collect();
     let s_string: Signal<String> = stream.map(|v| format!("{} ", v)).collect();
 
-    sink.feed(&[1, 3, -42, 2]);
+    sink.feed([1, 3, -42, 2]);
 
     assert_eq!(s_vec.sample(), [1, 3, -42, 2]);
     assert_eq!(s_vecdq.sample(), [1, 3, -42, 2]);
@@ -328,6 +328,283 @@ fn stream_send_order() {
     assert_eq!(result.sample(), [1, 2, 3]);
 }
 
+#[test]
+fn stream_end() {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    let sink: Sink<i32> = Sink::new();
+    let stream = sink.stream();
+    let sum = stream.fold(0, |acc, n| acc + *n);
+
+    let ended = Arc::new(AtomicBool::new(false));
+    let ended_ = ended.clone();
+    stream.on_end(move || ended_.store(true, Ordering::SeqCst));
+
+    sink.feed([1, 2, 3]);
+    assert_eq!(sum.sample(), 6);
+    assert!(!ended.load(Ordering::SeqCst));
+
+    sink.end();
+    assert!(ended.load(Ordering::SeqCst));
+
+    // a stream that has ended must reject/ignore later sends
+    sink.send(4);
+    assert_eq!(sum.sample(), 6);
+}
+
+#[test]
+fn end_called_from_own_observer_does_not_deadlock() {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    let sink: Sink<i32> = Sink::new();
+    let cloned = sink.clone();
+    let stream = sink.stream();
+    let sum = stream.fold(0, |acc, n| acc + *n);
+
+    let ended = Arc::new(AtomicBool::new(false));
+    let ended_ = ended.clone();
+    stream.on_end(move || ended_.store(true, Ordering::SeqCst));
+
+    // calling `end()` on a stream from inside one of that very stream's own
+    // observers (here, `inspect`) must not deadlock - the stream's observer
+    // lock can't still be held while the observer callback runs.
+    let watcher = stream.inspect(move |n| {
+        if *n == 1 {
+            cloned.end();
+        }
+    });
+    // keep `watcher` alive for the duration of the send below
+    let _keep = watcher.hold(0);
+
+    sink.send(1);
+    assert!(ended.load(Ordering::SeqCst));
+    assert_eq!(sum.sample(), 1);
+}
+
+#[test]
+fn register_from_own_observer_does_not_deadlock() {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{Arc, Mutex};
+
+    let sink: Sink<i32> = Sink::new();
+    let stream = sink.stream();
+    let seen_late = Arc::new(AtomicBool::new(false));
+    let seen_late_ = seen_late.clone();
+
+    // registering a *new* observer on `stream` (via `inspect`) from inside
+    // an existing observer callback on that same stream must not deadlock
+    // either - it's the same self-referential lock as above, just through
+    // `register()` instead of `end()`.
+    let late = Arc::new(Mutex::new(None));
+    let late_for_closure = late.clone();
+    let stream_for_closure = stream.clone();
+    let _first = stream.inspect(move |_n| {
+        let mut late = late_for_closure.lock().unwrap();
+        if late.is_none() {
+            let seen_late_ = seen_late_.clone();
+            *late = Some(
+                stream_for_closure
+                    .inspect(move |_| seen_late_.store(true, Ordering::SeqCst)),
+            );
+        }
+    });
+
+    sink.send(1);
+    sink.send(2);
+    assert!(seen_late.load(Ordering::SeqCst));
+}
+
+#[test]
+fn merge_end() {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    let sink1: Sink<i32> = Sink::new();
+    let sink2: Sink<i32> = Sink::new();
+    let merged = sink1.stream().merge(&sink2.stream());
+
+    let ended = Arc::new(AtomicBool::new(false));
+    let ended_ = ended.clone();
+    merged.on_end(move || ended_.store(true, Ordering::SeqCst));
+
+    sink1.end();
+    assert!(
+        !ended.load(Ordering::SeqCst),
+        "merge must wait for both parents to end"
+    );
+
+    sink2.end();
+    assert!(ended.load(Ordering::SeqCst));
+}
+
+#[test]
+fn stream_switch() {
+    let outer: Sink<Stream<i32>> = Sink::new();
+    let switched = outer.stream().switch();
+    let result = switched.collect::<Vec<_>>();
+
+    let inner1: Sink<i32> = Sink::new();
+    let inner2: Sink<i32> = Sink::new();
+
+    outer.send(inner1.stream());
+    inner1.send(1);
+    inner1.send(2);
+
+    outer.send(inner2.stream());
+    inner1.send(99); // no longer forwarded, `switch` disconnected from inner1
+    inner2.send(3);
+
+    assert_eq!(result.sample(), [1, 2, 3]);
+}
+
+#[test]
+fn stream_flat_map() {
+    use std::sync::{Arc, Mutex};
+
+    let inner_sinks: Arc<Mutex<Vec<Sink<i32>>>> = Arc::new(Mutex::new(Vec::new()));
+    let inner_sinks_ = inner_sinks.clone();
+
+    let outer: Sink<()> = Sink::new();
+    let result = outer
+        .stream()
+        .flat_map(move |_| {
+            let inner = Sink::new();
+            inner_sinks_.lock().unwrap().push(inner.clone());
+            inner.stream()
+        })
+        .collect::<Vec<_>>();
+
+    outer.send(());
+    inner_sinks.lock().unwrap()[0].send(1);
+    inner_sinks.lock().unwrap()[0].send(2);
+
+    outer.send(());
+    inner_sinks.lock().unwrap()[0].send(99); // stale inner stream, already switched away
+    inner_sinks.lock().unwrap()[1].send(3);
+
+    assert_eq!(result.sample(), [1, 2, 3]);
+}
+
+#[test]
+fn stream_flatten_concurrent() {
+    let outer: Sink<Stream<i32>> = Sink::new();
+    let flattened = outer.stream().flatten_concurrent();
+    let result = flattened.collect::<Vec<_>>();
+
+    let inner1: Sink<i32> = Sink::new();
+    let inner2: Sink<i32> = Sink::new();
+
+    outer.send(inner1.stream());
+    inner1.send(1);
+
+    outer.send(inner2.stream());
+    inner1.send(2); // unlike `switch`, inner1 stays connected
+    inner2.send(3);
+
+    assert_eq!(result.sample(), [1, 2, 3]);
+}
+
+#[test]
+fn stream_take_skip() {
+    let sink: Sink<i32> = Sink::new();
+    let stream = sink.stream();
+
+    let s_take = stream.take(3).collect::<Vec<_>>();
+    let s_skip = stream.skip(2).collect::<Vec<_>>();
+    let s_take_while = stream.take_while(|n| *n < 10).collect::<Vec<_>>();
+    let s_skip_while = stream.skip_while(|n| *n < 10).collect::<Vec<_>>();
+
+    sink.feed([1, 5, 20, 3, 8, 30]);
+
+    assert_eq!(s_take.sample(), [1, 5, 20]);
+    assert_eq!(s_skip.sample(), [20, 3, 8, 30]);
+    assert_eq!(s_take_while.sample(), [1, 5]);
+    assert_eq!(s_skip_while.sample(), [20, 3, 8, 30]);
+}
+
+#[test]
+fn take_disconnects() {
+    use std::sync::{Arc, RwLock};
+
+    let sink: Sink<i32> = Sink::new();
+    let cell = Arc::new(RwLock::new(0));
+    let cell_ = cell.clone();
+    let taken = sink
+        .stream()
+        .take(2)
+        .inspect(move |n| *cell_.write().unwrap() = *n);
+    let result = taken.collect::<Vec<_>>();
+
+    sink.send(1);
+    sink.send(2);
+    sink.send(3); // `take` has unregistered itself from the source by now,
+                  // so `inspect` downstream of it never sees this value
+
+    assert_eq!(result.sample(), [1, 2]);
+    assert_eq!(*cell.read().unwrap(), 2);
+}
+
+#[test]
+fn take_zero_ends_immediately() {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    let sink: Sink<i32> = Sink::new();
+    let taken = sink.stream().take(0);
+
+    let ended = Arc::new(AtomicBool::new(false));
+    let ended_ = ended.clone();
+    taken.on_end(move || ended_.store(true, Ordering::SeqCst));
+
+    // unlike every other `n`, there's no value left to wait for: `take(0)`
+    // must end up front rather than only on the (never-arriving) 0th value.
+    assert!(ended.load(Ordering::SeqCst));
+
+    let result = taken.collect::<Vec<_>>();
+    sink.send(1);
+    assert_eq!(result.sample(), Vec::<i32>::new());
+}
+
+#[test]
+fn stream_result_combinators() {
+    let sink: Sink<Result<i32, &'static str>> = Sink::new();
+    let stream = sink.stream();
+
+    let s_ok = stream.map_ok(|n| n * 2).collect::<Vec<_>>();
+    let s_err = stream.map_err(|e| e.len()).collect::<Vec<_>>();
+    let s_and_then = stream
+        .and_then(|n| if *n > 0 { Ok(*n) } else { Err("negative") })
+        .collect::<Vec<_>>();
+    let s_or_else = stream.or_else(|_| Ok(-1)).collect::<Vec<_>>();
+    let s_try_fold = stream.try_fold(0, |acc, n| acc + *n);
+
+    sink.feed(vec![Ok(3), Err("boom"), Ok(-5)]);
+
+    assert_eq!(s_ok.sample(), [Ok(6), Err("boom"), Ok(-10)]);
+    assert_eq!(s_err.sample(), [Ok(3), Err(4), Ok(-5)]);
+    assert_eq!(s_and_then.sample(), [Ok(3), Err("boom"), Err("negative")]);
+    assert_eq!(s_or_else.sample(), [Ok(3), Ok(-1), Ok(-5)]);
+    // the first `Err` latches the fold result, later `Ok`s are ignored
+    assert_eq!(s_try_fold.sample(), Err("boom"));
+}
+
+#[test]
+fn stream_remember() {
+    let sink: Sink<i32> = Sink::new();
+    let remembered = sink.stream().remember();
+
+    sink.send(1);
+    sink.send(2);
+
+    // a subscriber registered after values have flowed replays the last one
+    let late = remembered.collect::<Vec<_>>();
+    sink.send(3);
+
+    assert_eq!(late.sample(), [2, 3]);
+}
+
 #[cfg(feature = "lazycell")]
 #[test]
 fn signal_cyclic() {